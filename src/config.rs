@@ -0,0 +1,42 @@
+//! TOML configuration for reusable servo profiles and named motion sequences, loaded via
+//! `--config` so repeatable setups don't need to be retyped as long `--angles` lists.
+
+use crate::{Backend, BcmPin};
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+/// One step of a named sequence: an angle in degrees held for a time in milliseconds.
+#[derive(Deserialize, Clone, Copy)]
+pub(crate) struct Step {
+    pub(crate) angle: u64,
+    pub(crate) time: u64,
+}
+
+/// A servo's pin and calibration, configured under `[servo.<name>]`. Fields left unset fall
+/// back to the CLI's own defaults, so a profile only needs to state what's non-standard.
+#[derive(Deserialize, Clone)]
+pub(crate) struct ServoProfile {
+    pub(crate) pin: BcmPin,
+    pub(crate) frequency: Option<u64>,
+    pub(crate) backend: Option<Backend>,
+    pub(crate) min_pulse_us: Option<u64>,
+    pub(crate) max_pulse_us: Option<u64>,
+    pub(crate) range_degrees: Option<f64>,
+}
+
+/// The parsed contents of a `--config` file: named servo profiles and named sequences,
+/// e.g. `[servo.arm]` and `[[sequence.wave]]`.
+#[derive(Deserialize)]
+pub(crate) struct Config {
+    #[serde(default)]
+    pub(crate) servo: HashMap<String, ServoProfile>,
+    #[serde(default)]
+    pub(crate) sequence: HashMap<String, Vec<Step>>,
+}
+
+/// Reads and parses a `--config` file.
+pub(crate) fn load(path: &Path) -> Result<Config, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+
+    Ok(toml::from_str(&text)?)
+}
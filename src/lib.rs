@@ -2,15 +2,34 @@
 //!
 #![deny(unsafe_code, missing_docs)]
 
+mod config;
 mod log_macros;
+mod server;
 
 use clap::{Parser, ValueEnum};
 use core::fmt::Arguments;
-use rppal::gpio::Gpio;
-use std::{error::Error, time::Duration};
+use rppal::gpio::{Gpio, OutputPin};
+use rppal::pwm::{Channel, Polarity, Pwm};
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error, path::PathBuf, time::Duration};
+
+/// Default PWM frequency, in Hz, when neither `--frequency` nor a `--config` profile sets one.
+const DEFAULT_FREQUENCY: u64 = 50;
+/// Default pulse width, in microseconds, corresponding to 0°.
+const DEFAULT_MIN_PULSE_US: u64 = 500;
+/// Default pulse width, in microseconds, corresponding to `DEFAULT_RANGE_DEGREES`.
+const DEFAULT_MAX_PULSE_US: u64 = 2500;
+/// Default full angular travel of a servo, in degrees.
+const DEFAULT_RANGE_DEGREES: f64 = 180.0;
+/// Largest number of steps `--loop` is allowed to expand a `--config` sequence to, so a
+/// mistyped loop count errors out instead of hanging or exhausting memory.
+const MAX_SEQUENCE_STEPS: usize = 1_000_000;
 
 /// This trait defines the logging interface for the RppalSoftpwmTool.
-pub trait RppalSoftpwmLog {
+///
+/// `Sync` is required so a single logger can be shared across the threads that drive
+/// concurrent servo sequences.
+pub trait RppalSoftpwmLog: Sync {
     /// Output a message to the log.
     fn output(self: &Self, args: Arguments);
     /// Output a warning message to the log.
@@ -24,7 +43,7 @@ pub struct RppalSoftpwmTool<'a> {
     log: &'a dyn RppalSoftpwmLog,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
 #[repr(u8)]
 enum BcmPin {
     Pin1 = 1,
@@ -56,9 +75,202 @@ enum BcmPin {
     Pin27,
 }
 
-const DUTY_CYCLE_0_DEGREES: f64 = 2.5;
-const DUTY_CYCLE_180_DEGREES: f64 = 12.5;
-const DUTY_CYCLE_RANGE: f64 = DUTY_CYCLE_180_DEGREES - DUTY_CYCLE_0_DEGREES;
+impl TryFrom<u8> for BcmPin {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use BcmPin::*;
+
+        Ok(match value {
+            1 => Pin1,
+            2 => Pin2,
+            3 => Pin3,
+            4 => Pin4,
+            5 => Pin5,
+            6 => Pin6,
+            7 => Pin7,
+            8 => Pin8,
+            9 => Pin9,
+            10 => Pin10,
+            11 => Pin11,
+            12 => Pin12,
+            13 => Pin13,
+            14 => Pin14,
+            15 => Pin15,
+            16 => Pin16,
+            17 => Pin17,
+            18 => Pin18,
+            19 => Pin19,
+            20 => Pin20,
+            21 => Pin21,
+            22 => Pin22,
+            23 => Pin23,
+            24 => Pin24,
+            25 => Pin25,
+            26 => Pin26,
+            27 => Pin27,
+            _ => return Err(format!("unsupported BCM pin {}", value)),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for BcmPin {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        BcmPin::try_from(u8::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The PWM implementation used to drive the pin.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    /// Bit-bang the signal on the GPIO pin via `OutputPin::set_pwm_frequency`.
+    Soft,
+    /// Drive the signal with the BCM hardware PWM peripheral, when the pin supports it.
+    Hard,
+}
+
+/// Converts an angle in degrees to a pulse width, given the servo's calibrated range.
+fn degrees_to_pulse_width(
+    degrees: f64,
+    min_pulse_us: u64,
+    max_pulse_us: u64,
+    range_degrees: f64,
+) -> Duration {
+    let pulse_us = min_pulse_us as f64
+        + (degrees / range_degrees) * (max_pulse_us as f64 - min_pulse_us as f64);
+
+    Duration::from_secs_f64(pulse_us / 1_000_000.0)
+}
+
+/// Sub-step period used to interpolate between angle targets when `--slew` is set.
+const SLEW_STEP: Duration = Duration::from_millis(20);
+
+/// Expands each `(angle, time)` step into a series of smaller steps that move from the
+/// previously commanded angle to `angle` at no more than `slew_deg_per_sec`, holding at
+/// `angle` for whatever remains of `time`. If `time` is too short to reach `angle`, the
+/// step ends wherever the slew rate allows and the next step starts from there.
+fn interpolate_angles(
+    steps: Vec<(u64, u64)>,
+    slew_deg_per_sec: f64,
+    min_pulse_us: u64,
+    max_pulse_us: u64,
+    range_degrees: f64,
+) -> Vec<(Duration, u64)> {
+    let step_ms = SLEW_STEP.as_millis() as u64;
+    let mut result = Vec::new();
+    let mut position = steps.first().map_or(0.0, |(angle, _)| *angle as f64);
+
+    for (angle, time) in steps {
+        let target = angle as f64;
+        let full_travel_ms =
+            (((target - position).abs() / slew_deg_per_sec) * 1000.0).round() as u64;
+        let travel_ms = full_travel_ms.min(time);
+        let reached = if travel_ms < full_travel_ms {
+            position + (target - position).signum() * slew_deg_per_sec * travel_ms as f64 / 1000.0
+        } else {
+            target
+        };
+        let step_count = travel_ms.div_ceil(step_ms);
+        let delta_per_step = if step_count == 0 {
+            0.0
+        } else {
+            (reached - position) / step_count as f64
+        };
+        let mut elapsed = 0;
+
+        for _ in 0..step_count {
+            let this_step = step_ms.min(travel_ms - elapsed);
+            position += delta_per_step;
+            elapsed += this_step;
+            result.push((
+                degrees_to_pulse_width(position, min_pulse_us, max_pulse_us, range_degrees),
+                this_step,
+            ));
+        }
+
+        position = reached;
+
+        let hold_ms = time - travel_ms;
+        if hold_ms > 0 {
+            result.push((
+                degrees_to_pulse_width(position, min_pulse_us, max_pulse_us, range_degrees),
+                hold_ms,
+            ));
+        }
+    }
+
+    result
+}
+
+/// Returns the hardware PWM channel wired to `pin`, if any.
+fn hardware_channel(pin: BcmPin) -> Option<Channel> {
+    match pin {
+        BcmPin::Pin12 | BcmPin::Pin18 => Some(Channel::Pwm0),
+        BcmPin::Pin13 | BcmPin::Pin19 => Some(Channel::Pwm1),
+        _ => None,
+    }
+}
+
+/// Checks that no two servos in `servos` would drive the same GPIO pin or, under the
+/// hardware backend, the same underlying PWM channel (GPIO12/GPIO18 share `Pwm0`, and
+/// GPIO13/GPIO19 share `Pwm1`), since two threads driving the same hardware concurrently
+/// would race each other.
+fn validate_distinct_servos(servos: &[(BcmPin, StepSource)], backend: Backend) -> Result<(), String> {
+    let mut pins = HashMap::new();
+    // `rppal::pwm::Channel` doesn't implement `Hash`, so key on its `u8` discriminant.
+    let mut channels: HashMap<u8, BcmPin> = HashMap::new();
+
+    for (pin, _) in servos {
+        if let Some(other) = pins.insert(*pin, *pin) {
+            return Err(format!("pin {} is driven by more than one servo", other as u8));
+        }
+
+        if backend == Backend::Hard {
+            if let Some(channel) = hardware_channel(*pin) {
+                if let Some(other) = channels.insert(channel as u8, *pin) {
+                    return Err(format!(
+                        "pins {} and {} share a hardware PWM channel and cannot be driven concurrently",
+                        other as u8, *pin as u8
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that every angle step sourced from `--angles`, `--servo`, or a `--config`
+/// sequence falls within `0..=range_degrees`, the same bound `server::validate_angle`
+/// enforces for the daemon's JSON commands.
+fn validate_angle_steps(servos: &[(BcmPin, StepSource)], range_degrees: f64) -> Result<(), String> {
+    for (_, source) in servos {
+        if let StepSource::Angles(steps) = source {
+            for (angle, _) in steps {
+                if *angle as f64 > range_degrees {
+                    return Err(format!(
+                        "angle {} exceeds configured --range-degrees {}",
+                        angle, range_degrees
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The PWM peripheral actually driving the pin, chosen by `Backend`.
+enum Driver {
+    /// Software PWM generated by toggling a GPIO output pin.
+    Soft(OutputPin),
+    /// Hardware PWM generated by the BCM peripheral.
+    Hard(Pwm),
+}
 
 #[derive(Parser)]
 #[clap(version, about, long_about = None)]
@@ -68,16 +280,64 @@ struct Cli {
     no_color: bool,
 
     /// BCM pin to use for PWM output
-    #[arg(long = "pin", short = 'p')]
-    pin: BcmPin,
+    #[arg(long = "pin", short = 'p', required_unless_present_any = ["servos", "listen"])]
+    pin: Option<BcmPin>,
 
-    /// Frequency of the PWM signal in Hz
-    #[arg(long = "frequency", short = 'f', default_value_t = 50)]
-    frequency: u64,
+    /// Frequency of the PWM signal in Hz [default: 50, or the servo's --config profile]
+    #[arg(long = "frequency", short = 'f')]
+    frequency: Option<u64>,
+
+    /// PWM backend to drive the pin with [default: soft, or the servo's --config profile]
+    #[arg(long = "backend", short = 'b')]
+    backend: Option<Backend>,
+
+    /// Pulse width in microseconds corresponding to 0° [default: 500, or the servo's --config profile]
+    #[arg(long = "min-pulse-us")]
+    min_pulse_us: Option<u64>,
+
+    /// Pulse width in microseconds corresponding to `--range-degrees` [default: 2500, or the servo's --config profile]
+    #[arg(long = "max-pulse-us")]
+    max_pulse_us: Option<u64>,
+
+    /// Full angular travel of the servo, from `--min-pulse-us` to `--max-pulse-us` [default: 180, or the servo's --config profile]
+    #[arg(long = "range-degrees")]
+    range_degrees: Option<f64>,
 
     /// Sequence of angles in degrees and times in milliseconds
-    #[arg(long = "angles", short = 'a', value_name = "ANGLE:TIME", value_parser = parse_angle_time, value_delimiter = ',', num_args = 1..)]
+    #[arg(long = "angles", short = 'a', value_name = "ANGLE:TIME", value_parser = parse_angle_time, value_delimiter = ',', num_args = 1.., conflicts_with = "pulse")]
     angles: Vec<(u64, u64)>,
+
+    /// Sequence of raw pulse widths in microseconds and times in milliseconds, for calibration or non-servo PWM devices
+    #[arg(long = "pulse", value_name = "PULSE_US:TIME", value_parser = parse_pulse_time, value_delimiter = ',', num_args = 1..)]
+    pulse: Vec<(u64, u64)>,
+
+    /// Additional servo to drive concurrently with --pin, as PIN=ANGLE:TIME,ANGLE:TIME,... (repeatable)
+    #[arg(long = "servo", short = 's', value_name = "PIN=ANGLE:TIME,...", value_parser = parse_servo_sequence)]
+    servos: Vec<(BcmPin, Vec<(u64, u64)>)>,
+
+    /// Maximum angular velocity in degrees/sec; angle steps are interpolated smoothly instead of stepped instantly
+    #[arg(long = "slew", value_name = "DEG_PER_SEC")]
+    slew: Option<f64>,
+
+    /// Run as a daemon, accepting newline-delimited JSON commands from TCP clients at ADDR instead of a fixed sequence
+    #[arg(long = "listen", value_name = "ADDR", conflicts_with_all = ["pin", "servos", "angles", "pulse", "slew"])]
+    listen: Option<String>,
+
+    /// Load servo profiles and named motion sequences from a TOML file
+    #[arg(long = "config", value_name = "PATH", conflicts_with_all = ["pin", "servos"])]
+    config: Option<PathBuf>,
+
+    /// Servo profile to drive, from `--config`
+    #[arg(long = "servo-name", value_name = "NAME", requires = "config")]
+    servo_name: Option<String>,
+
+    /// Named motion sequence to run, from `--config`, in place of --angles/--pulse
+    #[arg(long = "sequence", value_name = "NAME", requires = "config", conflicts_with_all = ["angles", "pulse"])]
+    sequence: Option<String>,
+
+    /// Number of times to repeat --sequence
+    #[arg(long = "loop", value_name = "N", default_value_t = 1, requires = "sequence")]
+    loop_count: u64,
 }
 
 fn parse_angle_time(s: &str) -> Result<(u64, u64), String> {
@@ -85,14 +345,39 @@ fn parse_angle_time(s: &str) -> Result<(u64, u64), String> {
     if parts.len() != 2 {
         return Err("Invalid format".to_string());
     }
+    // The upper bound depends on the resolved `--range-degrees`, which isn't known yet at
+    // parse time, so it's checked later in `run()` via `validate_angle_steps`.
     let angle = parts[0].parse().map_err(|_| "Invalid angle".to_string())?;
+    let time = parts[1].parse().map_err(|_| "Invalid time".to_string())?;
+    Ok((angle, time))
+}
 
-    if angle > 180 {
-        return Err("Angle must be between 0 and 180".to_string());
+fn parse_pulse_time(s: &str) -> Result<(u64, u64), String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() != 2 {
+        return Err("Invalid format".to_string());
     }
-
+    let pulse_us = parts[0]
+        .parse()
+        .map_err(|_| "Invalid pulse width".to_string())?;
     let time = parts[1].parse().map_err(|_| "Invalid time".to_string())?;
-    Ok((angle, time))
+    Ok((pulse_us, time))
+}
+
+fn parse_servo_sequence(s: &str) -> Result<(BcmPin, Vec<(u64, u64)>), String> {
+    let (pin, steps) = s.split_once('=').ok_or("Invalid format")?;
+    let pin = BcmPin::from_str(pin, true)?;
+    let steps = steps.split(',').map(parse_angle_time).collect::<Result<_, _>>()?;
+
+    Ok((pin, steps))
+}
+
+/// The angle/time or pulse/time steps making up one servo's sequence, before calibration is applied.
+enum StepSource {
+    /// Angles in degrees, to be mapped to pulse widths via the servo's calibration.
+    Angles(Vec<(u64, u64)>),
+    /// Raw pulse widths in microseconds.
+    Pulses(Vec<(u64, u64)>),
 }
 
 impl<'a> RppalSoftpwmTool<'a> {
@@ -101,6 +386,112 @@ impl<'a> RppalSoftpwmTool<'a> {
         RppalSoftpwmTool { log }
     }
 
+    /// Opens `pin` as a GPIO output for software PWM, with reset-on-drop disabled.
+    fn soft_pin(pin: BcmPin) -> Result<OutputPin, Box<dyn Error + Send + Sync>> {
+        let mut pin = Gpio::new()?.get(pin as u8)?.into_output();
+
+        pin.set_reset_on_drop(false);
+
+        Ok(pin)
+    }
+
+    /// Resolves a servo's raw CLI steps to pulse widths, applying calibration to angle steps
+    /// and, when `slew_deg_per_sec` is set, interpolating between them.
+    fn resolve_sequence(
+        source: StepSource,
+        min_pulse_us: u64,
+        max_pulse_us: u64,
+        range_degrees: f64,
+        slew_deg_per_sec: Option<f64>,
+    ) -> Vec<(Duration, u64)> {
+        match source {
+            StepSource::Pulses(steps) => steps
+                .into_iter()
+                .map(|(pulse_us, time)| (Duration::from_micros(pulse_us), time))
+                .collect(),
+            StepSource::Angles(steps) => match slew_deg_per_sec {
+                Some(slew) if slew > 0.0 => {
+                    interpolate_angles(steps, slew, min_pulse_us, max_pulse_us, range_degrees)
+                }
+                _ => steps
+                    .into_iter()
+                    .map(|(angle, time)| {
+                        (
+                            degrees_to_pulse_width(angle as f64, min_pulse_us, max_pulse_us, range_degrees),
+                            time,
+                        )
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// Creates the `Driver` for `pin`, falling back to software PWM with a warning if
+    /// `backend` is `Hard` but `pin` has no hardware PWM channel.
+    fn create_driver(
+        log: &dyn RppalSoftpwmLog,
+        pin: BcmPin,
+        backend: Backend,
+        period: Duration,
+    ) -> Result<Driver, Box<dyn Error + Send + Sync>> {
+        Ok(match backend {
+            Backend::Hard => match hardware_channel(pin) {
+                Some(channel) => {
+                    Driver::Hard(Pwm::with_period(channel, period, Duration::from_secs(0), Polarity::Normal, true)?)
+                }
+                None => {
+                    warning!(
+                        log,
+                        "pin {} has no hardware PWM channel, falling back to software PWM",
+                        pin as u8
+                    );
+                    Driver::Soft(Self::soft_pin(pin)?)
+                }
+            },
+            Backend::Soft => Driver::Soft(Self::soft_pin(pin)?),
+        })
+    }
+
+    /// Drives a single servo through `sequence`, one step at a time, until it is exhausted.
+    fn drive_servo(
+        log: &dyn RppalSoftpwmLog,
+        pin: BcmPin,
+        backend: Backend,
+        period: Duration,
+        sequence: Vec<(Duration, u64)>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut driver = Self::create_driver(log, pin, backend, period)?;
+
+        for (pulse_width, time) in sequence {
+            output!(
+                log,
+                "pin {}: {} µs for {} ms",
+                pin as u8,
+                pulse_width.as_micros(),
+                time
+            );
+
+            match &mut driver {
+                Driver::Soft(soft_pin) => {
+                    let duty_cycle = pulse_width.as_secs_f64() / period.as_secs_f64();
+                    soft_pin.set_pwm_frequency(1.0 / period.as_secs_f64(), duty_cycle)?;
+                }
+                Driver::Hard(pwm) => pwm.set_pulse_width(pulse_width)?,
+            }
+
+            std::thread::sleep(Duration::from_millis(time));
+        }
+
+        output!(log, "pin {}: done", pin as u8);
+
+        match driver {
+            Driver::Soft(mut soft_pin) => soft_pin.clear_pwm()?,
+            Driver::Hard(pwm) => pwm.disable()?,
+        }
+
+        Ok(())
+    }
+
     /// Run the tool with the given arguments.
     pub fn run(
         self: &mut Self,
@@ -113,23 +504,143 @@ impl<'a> RppalSoftpwmTool<'a> {
                 return Ok(());
             }
         };
-        let mut latch_pin = Gpio::new()?.get(cli.pin as u8)?.into_output();
 
-        latch_pin.set_reset_on_drop(false);
+        let mut config_servo: Option<(BcmPin, StepSource)> = None;
+        let mut profile: Option<config::ServoProfile> = None;
+
+        if let Some(path) = &cli.config {
+            let config = config::load(path)?;
+            let servo_name = cli
+                .servo_name
+                .as_deref()
+                .ok_or("--servo-name is required with --config")?;
+            let servo_profile = config
+                .servo
+                .get(servo_name)
+                .ok_or_else(|| format!("no servo named '{}' in --config", servo_name))?
+                .clone();
+
+            let steps = match &cli.sequence {
+                Some(sequence_name) => {
+                    let steps = config
+                        .sequence
+                        .get(sequence_name)
+                        .ok_or_else(|| format!("no sequence named '{}' in --config", sequence_name))?;
+
+                    let repeated_len = steps
+                        .len()
+                        .checked_mul(cli.loop_count as usize)
+                        .filter(|len| *len <= MAX_SEQUENCE_STEPS)
+                        .ok_or_else(|| {
+                            format!(
+                                "--loop {} would expand sequence '{}' to more than {} steps",
+                                cli.loop_count, sequence_name, MAX_SEQUENCE_STEPS
+                            )
+                        })?;
+
+                    steps
+                        .iter()
+                        .cycle()
+                        .take(repeated_len)
+                        .map(|step| (step.angle, step.time))
+                        .collect()
+                }
+                None => Vec::new(),
+            };
 
-        fn degrees_to_duty_cycle(degrees: f64) -> f64 {
-            (degrees * (DUTY_CYCLE_RANGE / 180.0) + DUTY_CYCLE_0_DEGREES) / 100.0
+            config_servo = Some((servo_profile.pin, StepSource::Angles(steps)));
+            profile = Some(servo_profile);
         }
 
-        for (angle, time) in cli.angles {
-            output!(self.log, "{}° for {} ms", angle, time);
-            latch_pin
-                .set_pwm_frequency(cli.frequency as f64, degrees_to_duty_cycle(angle as f64))?;
-            std::thread::sleep(Duration::from_millis(time));
+        let backend = cli
+            .backend
+            .or(profile.as_ref().and_then(|p| p.backend))
+            .unwrap_or(Backend::Soft);
+        let frequency = cli
+            .frequency
+            .or(profile.as_ref().and_then(|p| p.frequency))
+            .unwrap_or(DEFAULT_FREQUENCY);
+        let min_pulse_us = cli
+            .min_pulse_us
+            .or(profile.as_ref().and_then(|p| p.min_pulse_us))
+            .unwrap_or(DEFAULT_MIN_PULSE_US);
+        let max_pulse_us = cli
+            .max_pulse_us
+            .or(profile.as_ref().and_then(|p| p.max_pulse_us))
+            .unwrap_or(DEFAULT_MAX_PULSE_US);
+        let range_degrees = cli
+            .range_degrees
+            .or(profile.as_ref().and_then(|p| p.range_degrees))
+            .unwrap_or(DEFAULT_RANGE_DEGREES);
+
+        if range_degrees <= 0.0 {
+            return Err(format!("--range-degrees must be greater than 0, got {}", range_degrees).into());
+        }
+
+        let period = Duration::from_secs_f64(1.0 / frequency as f64);
+
+        if let Some(addr) = cli.listen {
+            return server::listen(
+                self.log,
+                &addr,
+                server::ServoConfig {
+                    backend,
+                    period,
+                    min_pulse_us,
+                    max_pulse_us,
+                    range_degrees,
+                },
+            );
         }
 
-        output!(self.log, "Done");
-        latch_pin.clear_pwm()?;
+        let mut servos: Vec<(BcmPin, StepSource)> = Vec::new();
+
+        if let Some(servo) = config_servo {
+            servos.push(servo);
+        } else if let Some(pin) = cli.pin {
+            servos.push((
+                pin,
+                if !cli.pulse.is_empty() {
+                    StepSource::Pulses(cli.pulse)
+                } else {
+                    StepSource::Angles(cli.angles)
+                },
+            ));
+        }
+        servos.extend(
+            cli.servos
+                .into_iter()
+                .map(|(pin, angles)| (pin, StepSource::Angles(angles))),
+        );
+
+        validate_distinct_servos(&servos, backend)?;
+        validate_angle_steps(&servos, range_degrees)?;
+
+        let log = self.log;
+
+        std::thread::scope(|scope| -> Result<(), Box<dyn Error + Send + Sync>> {
+            let handles: Vec<_> = servos
+                .into_iter()
+                .map(|(pin, source)| {
+                    let sequence = Self::resolve_sequence(
+                        source,
+                        min_pulse_us,
+                        max_pulse_us,
+                        range_degrees,
+                        cli.slew,
+                    );
+
+                    scope.spawn(move || Self::drive_servo(log, pin, backend, period, sequence))
+                })
+                .collect();
+
+            for handle in handles {
+                handle.join().expect("servo thread panicked")?;
+            }
+
+            Ok(())
+        })
+        .map_err(|err| -> Box<dyn Error> { err })?;
 
         Ok(())
     }
@@ -161,4 +672,134 @@ mod tests {
 
         tool.run(args).unwrap();
     }
+
+    fn pulse_us(degrees: f64) -> u128 {
+        degrees_to_pulse_width(
+            degrees,
+            DEFAULT_MIN_PULSE_US,
+            DEFAULT_MAX_PULSE_US,
+            DEFAULT_RANGE_DEGREES,
+        )
+        .as_micros()
+    }
+
+    fn assert_pulse_us_near(actual: u128, expected_degrees: f64) {
+        let expected = pulse_us(expected_degrees);
+        let diff = actual.abs_diff(expected);
+
+        assert!(
+            diff <= 1,
+            "pulse width {} µs not within 1 µs of {} µs ({} degrees)",
+            actual,
+            expected,
+            expected_degrees
+        );
+    }
+
+    #[test]
+    fn interpolate_angles_exact_division() {
+        let steps = interpolate_angles(
+            vec![(0, 1000), (90, 1000)],
+            90.0,
+            DEFAULT_MIN_PULSE_US,
+            DEFAULT_MAX_PULSE_US,
+            DEFAULT_RANGE_DEGREES,
+        );
+
+        // First segment doesn't move, so it's a single 1000ms hold at 0 degrees.
+        assert_eq!(steps[0], (Duration::from_micros(pulse_us(0.0) as u64), 1000));
+
+        // Second segment travels 0 -> 90 degrees at 90 deg/sec, which divides evenly into
+        // fifty 20ms sub-steps with no trailing hold.
+        assert_eq!(steps.len(), 1 + 50);
+        assert_eq!(steps[1..].iter().map(|(_, time)| time).sum::<u64>(), 1000);
+        for (_, time) in &steps[1..] {
+            assert_eq!(*time, 20);
+        }
+        let (last_pulse, _) = steps.last().unwrap();
+        assert_pulse_us_near(last_pulse.as_micros(), 90.0);
+    }
+
+    #[test]
+    fn interpolate_angles_non_multiple_of_step() {
+        let steps = interpolate_angles(
+            vec![(0, 10), (45, 500)],
+            100.0,
+            DEFAULT_MIN_PULSE_US,
+            DEFAULT_MAX_PULSE_US,
+            DEFAULT_RANGE_DEGREES,
+        );
+
+        // 45 degrees at 100 deg/sec takes 450ms: twenty-two 20ms sub-steps, one 10ms
+        // leftover sub-step, then a 50ms hold to fill out the remaining 500ms step.
+        let second_segment = &steps[1..];
+        assert_eq!(second_segment.len(), 22 + 1 + 1);
+        assert_eq!(second_segment[22].1, 10);
+        assert_eq!(second_segment.last().unwrap().1, 50);
+        assert_pulse_us_near(second_segment.last().unwrap().0.as_micros(), 45.0);
+        assert_eq!(second_segment.iter().map(|(_, time)| time).sum::<u64>(), 500);
+    }
+
+    #[test]
+    fn interpolate_angles_clamps_travel_to_step_time() {
+        let steps = interpolate_angles(
+            vec![(0, 0), (90, 200)],
+            10.0,
+            DEFAULT_MIN_PULSE_US,
+            DEFAULT_MAX_PULSE_US,
+            DEFAULT_RANGE_DEGREES,
+        );
+
+        // At 10 deg/sec, 90 degrees would take 9000ms, but the step only lasts 200ms, so
+        // travel is clamped to the step's own time with no trailing hold and the servo only
+        // gets as far as 2 degrees.
+        assert_eq!(steps.iter().map(|(_, time)| time).sum::<u64>(), 200);
+        assert_pulse_us_near(steps.last().unwrap().0.as_micros(), 2.0);
+    }
+
+    #[test]
+    fn interpolate_angles_empty_input() {
+        let steps = interpolate_angles(
+            vec![],
+            45.0,
+            DEFAULT_MIN_PULSE_US,
+            DEFAULT_MAX_PULSE_US,
+            DEFAULT_RANGE_DEGREES,
+        );
+
+        assert!(steps.is_empty());
+    }
+
+    #[test]
+    fn validate_distinct_servos_rejects_duplicate_pin() {
+        let servos = vec![
+            (BcmPin::Pin18, StepSource::Angles(vec![])),
+            (BcmPin::Pin18, StepSource::Angles(vec![])),
+        ];
+
+        assert!(validate_distinct_servos(&servos, Backend::Soft).is_err());
+    }
+
+    #[test]
+    fn validate_distinct_servos_rejects_shared_hardware_channel() {
+        // GPIO12 and GPIO18 both drive Pwm0, so they can't be backed by hardware PWM
+        // concurrently, even though they're different pins.
+        let servos = vec![
+            (BcmPin::Pin12, StepSource::Angles(vec![])),
+            (BcmPin::Pin18, StepSource::Angles(vec![])),
+        ];
+
+        assert!(validate_distinct_servos(&servos, Backend::Hard).is_err());
+        assert!(validate_distinct_servos(&servos, Backend::Soft).is_ok());
+    }
+
+    #[test]
+    fn validate_angle_steps_rejects_angle_beyond_range() {
+        // This is the same `StepSource::Angles` shape `run()` builds for a `--config`
+        // sequence's steps, not just for `--angles`/`--servo`.
+        let servos = vec![(BcmPin::Pin18, StepSource::Angles(vec![(270, 1000)]))];
+
+        assert!(validate_angle_steps(&servos, DEFAULT_RANGE_DEGREES).is_err());
+        assert!(validate_angle_steps(&servos, 270.0).is_ok());
+    }
 }
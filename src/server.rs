@@ -0,0 +1,300 @@
+//! Daemon mode: accepts TCP clients that send newline-delimited JSON commands and
+//! receive newline-delimited JSON acknowledgements and, once a client asks for them,
+//! periodic state reports.
+
+use crate::{
+    degrees_to_pulse_width, error, output, warning, Backend, BcmPin, Driver, RppalSoftpwmLog,
+    RppalSoftpwmTool,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// PWM settings shared by every servo the daemon drives; there is currently no way for
+/// a client to calibrate an individual servo differently.
+#[derive(Copy, Clone)]
+pub(crate) struct ServoConfig {
+    pub(crate) backend: Backend,
+    pub(crate) period: Duration,
+    pub(crate) min_pulse_us: u64,
+    pub(crate) max_pulse_us: u64,
+    pub(crate) range_degrees: f64,
+}
+
+/// A live servo driver plus the state last reported to clients.
+struct Servo {
+    driver: Driver,
+    angle: f64,
+    duty_cycle: f64,
+}
+
+type Servos = Arc<Mutex<HashMap<BcmPin, Servo>>>;
+
+/// A command sent by a client, one per line.
+#[derive(Deserialize)]
+struct Command {
+    pin: u8,
+    angle: Option<f64>,
+    sequence: Option<Vec<(f64, u64)>>,
+    report: Option<bool>,
+}
+
+/// The reply sent for every `Command` line.
+#[derive(Serialize)]
+struct Ack {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A periodic telemetry line, sent for every pin a client has asked to be reported on once
+/// that pin has been commanded at least once.
+#[derive(Serialize)]
+struct Report {
+    pin: u8,
+    angle: f64,
+    duty: f64,
+}
+
+/// How often report streams are refreshed.
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runs the daemon, accepting client connections on `addr` until the process is killed.
+pub(crate) fn listen(
+    log: &dyn RppalSoftpwmLog,
+    addr: &str,
+    config: ServoConfig,
+) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    let servos: Servos = Arc::new(Mutex::new(HashMap::new()));
+
+    output!(log, "listening on {}", addr);
+
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warning!(log, "{}", err);
+                    continue;
+                }
+            };
+            let servos = Arc::clone(&servos);
+
+            scope.spawn(move || {
+                if let Err(err) = handle_client(log, stream, servos, config) {
+                    error!(log, "{}", err);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Sets its `AtomicBool` to `false` on drop, so the reporting thread notices the owning
+/// `handle_client` call returned (for any reason) instead of sleeping forever.
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Services one client connection until it disconnects or a socket error occurs.
+fn handle_client(
+    log: &dyn RppalSoftpwmLog,
+    stream: TcpStream,
+    servos: Servos,
+    config: ServoConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let reporting: Arc<Mutex<HashSet<BcmPin>>> = Arc::new(Mutex::new(HashSet::new()));
+    let running = Arc::new(AtomicBool::new(true));
+    let _stop_on_drop = StopOnDrop(Arc::clone(&running));
+
+    {
+        let writer = Arc::clone(&writer);
+        let servos = Arc::clone(&servos);
+        let reporting = Arc::clone(&reporting);
+        let running = Arc::clone(&running);
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(REPORT_INTERVAL);
+
+            if !running.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let pins: Vec<BcmPin> = reporting.lock().unwrap().iter().copied().collect();
+            if pins.is_empty() {
+                continue;
+            }
+
+            let mut writer = match writer.lock() {
+                Ok(writer) => writer,
+                Err(_) => return,
+            };
+            let servos = servos.lock().unwrap();
+
+            for pin in pins {
+                if let Some(servo) = servos.get(&pin) {
+                    let report = Report {
+                        pin: pin as u8,
+                        angle: servo.angle,
+                        duty: servo.duty_cycle,
+                    };
+
+                    if write_line(&mut writer, &report).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let ack = match serde_json::from_str::<Command>(&line)
+            .map_err(|err| err.to_string())
+            .and_then(|command| apply_command(log, &servos, &reporting, config, command))
+        {
+            Ok(()) => Ack {
+                ok: true,
+                error: None,
+            },
+            Err(err) => Ack {
+                ok: false,
+                error: Some(err),
+            },
+        };
+
+        write_line(&mut writer.lock().unwrap(), &ack)?;
+    }
+
+    Ok(())
+}
+
+/// Applies one decoded `Command`, updating `reporting` and driving any requested motion.
+fn apply_command(
+    log: &dyn RppalSoftpwmLog,
+    servos: &Servos,
+    reporting: &Mutex<HashSet<BcmPin>>,
+    config: ServoConfig,
+    command: Command,
+) -> Result<(), String> {
+    let pin = BcmPin::try_from(command.pin)?;
+
+    if let Some(report) = command.report {
+        let mut reporting = reporting.lock().unwrap();
+        if report {
+            reporting.insert(pin);
+        } else {
+            reporting.remove(&pin);
+        }
+    }
+
+    if let Some(angle) = command.angle {
+        validate_angle(angle, config.range_degrees)?;
+        set_angle(log, servos, pin, config, angle).map_err(|err| err.to_string())?;
+    }
+
+    if let Some(sequence) = command.sequence {
+        for (angle, _) in &sequence {
+            validate_angle(*angle, config.range_degrees)?;
+        }
+
+        for (angle, time) in sequence {
+            set_angle(log, servos, pin, config, angle).map_err(|err| err.to_string())?;
+            std::thread::sleep(Duration::from_millis(time));
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects angles a client sends that would make `degrees_to_pulse_width` produce a
+/// negative, infinite, or `NaN` pulse width, which would panic `Duration::from_secs_f64`.
+fn validate_angle(angle: f64, range_degrees: f64) -> Result<(), String> {
+    if !angle.is_finite() {
+        return Err(format!("angle must be a finite number, got {}", angle));
+    }
+
+    if angle < 0.0 || angle > range_degrees {
+        return Err(format!(
+            "angle {} is out of range 0..={}",
+            angle, range_degrees
+        ));
+    }
+
+    Ok(())
+}
+
+/// Commands `pin` to `angle`, creating its driver on first use.
+fn set_angle(
+    log: &dyn RppalSoftpwmLog,
+    servos: &Servos,
+    pin: BcmPin,
+    config: ServoConfig,
+    angle: f64,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let pulse_width = degrees_to_pulse_width(
+        angle,
+        config.min_pulse_us,
+        config.max_pulse_us,
+        config.range_degrees,
+    );
+    let duty_cycle = pulse_width.as_secs_f64() / config.period.as_secs_f64();
+
+    let mut servos = servos.lock().unwrap();
+    let servo = match servos.entry(pin) {
+        std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+        std::collections::hash_map::Entry::Vacant(entry) => {
+            let driver = RppalSoftpwmTool::create_driver(log, pin, config.backend, config.period)?;
+
+            entry.insert(Servo {
+                driver,
+                angle,
+                duty_cycle,
+            })
+        }
+    };
+
+    match &mut servo.driver {
+        Driver::Soft(soft_pin) => {
+            soft_pin.set_pwm_frequency(1.0 / config.period.as_secs_f64(), duty_cycle)?
+        }
+        Driver::Hard(pwm) => pwm.set_pulse_width(pulse_width)?,
+    }
+
+    servo.angle = angle;
+    servo.duty_cycle = duty_cycle;
+
+    Ok(())
+}
+
+/// Serializes `value` to JSON and writes it, newline-terminated, to `writer`.
+fn write_line<T: Serialize>(
+    writer: &mut TcpStream,
+    value: &T,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+
+    Ok(())
+}